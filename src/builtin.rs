@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use super::error::Error;
+use super::modinfo::ModInfo;
+
+/// Reads `/lib/modules/<release>/modules.builtin.modinfo` and returns a
+/// `ModInfo` per statically-linked module.
+///
+/// The file concatenates the same NUL-separated `key=value` strings as
+/// the ELF `.modinfo` section, except every key is prefixed with the
+/// owning module name, e.g. `ext4.license=GPL\0ext4.parm=...`. We group
+/// entries by that leading `<module>.` prefix and feed each one through
+/// `ModInfo::apply_field`, the same dispatch `.modinfo` parsing uses.
+pub fn get_builtin_modinfo(release: &str) -> Result<HashMap<String, ModInfo>, Error> {
+    let path = format!("/lib/modules/{}/modules.builtin.modinfo", release);
+    let mut file = File::open(&path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+
+    let mut result: HashMap<String, ModInfo> = HashMap::new();
+    for one in contents.split(|chr| *chr == 0) {
+        if one.is_empty() {
+            continue;
+        }
+        let entry = String::from_utf8(one.to_vec())?;
+        let dot = match entry.find('.') {
+            Some(dot) => dot,
+            None => {
+                eprintln!("warning: {}: unprefixed builtin modinfo entry {}", &path, &entry);
+                continue;
+            }
+        };
+        let (module, field) = entry.split_at(dot);
+        let field = &field[1..];
+        result
+            .entry(module.to_owned())
+            .or_default()
+            .apply_field(field)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,59 @@
+use std::io::{Cursor, Read};
+
+use super::error::Error;
+
+/// Compression scheme used for a module file on disk, inferred from
+/// its filename suffix.
+pub enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+    Uncompressed,
+}
+
+/// The module file suffixes this crate knows how to decompress, in the
+/// order `get_module_paths` should look for them while walking a
+/// `/lib/modules/<release>` tree.
+pub const MODULE_SUFFIXES: &[&str] = &[".ko.gz", ".ko.xz", ".ko.zst", ".ko"];
+
+impl Compression {
+    /// Detects the compression scheme from a module's filename suffix.
+    pub fn from_filename(filename: &str) -> Option<Compression> {
+        if filename.ends_with(".ko.gz") {
+            Some(Compression::Gzip)
+        } else if filename.ends_with(".ko.xz") {
+            Some(Compression::Xz)
+        } else if filename.ends_with(".ko.zst") {
+            Some(Compression::Zstd)
+        } else if filename.ends_with(".ko") {
+            Some(Compression::Uncompressed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decompresses a module file's raw bytes into a buffer ready for the
+/// ELF parser, dispatching on the compression scheme detected from its
+/// filename.
+pub fn decompress(compression: Compression, compressed: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
+    let mut buf = Cursor::new(Vec::new());
+    match compression {
+        Compression::Gzip => {
+            let mut decoder = flate2::read::GzDecoder::new(compressed);
+            decoder.read_to_end(buf.get_mut())?;
+        }
+        Compression::Xz => {
+            let mut decoder = xz2::read::XzDecoder::new(compressed);
+            decoder.read_to_end(buf.get_mut())?;
+        }
+        Compression::Zstd => {
+            let mut decoder = zstd::stream::read::Decoder::new(compressed)?;
+            decoder.read_to_end(buf.get_mut())?;
+        }
+        Compression::Uncompressed => {
+            buf.get_mut().extend_from_slice(compressed);
+        }
+    }
+    Ok(buf)
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+
+use super::error::Error;
+use super::modinfo::ModInfo;
+use super::Module;
+
+/// Module dependency DAG built from the loaded-module set (`/proc/modules`)
+/// and the `depends=`/`softdep=` modinfo fields, used to compute correct
+/// load and unload ordering.
+///
+/// `softdep=` pre/post entries are only wired in as edges when the named
+/// module is actually present among the modules the graph was built
+/// from -- they're ordering hints, not hard requirements, so a missing
+/// softdep target is silently skipped rather than treated as an error.
+pub struct DependencyGraph {
+    forward: HashMap<String, Vec<String>>,
+    reverse: HashMap<String, Vec<String>>,
+    ref_counts: HashMap<String, usize>,
+}
+
+impl DependencyGraph {
+    pub fn build(loaded: &[Module], modinfo: &HashMap<String, ModInfo>) -> DependencyGraph {
+        let mut forward: HashMap<String, Vec<String>> = HashMap::new();
+        let mut reverse: HashMap<String, Vec<String>> = HashMap::new();
+        let mut ref_counts: HashMap<String, usize> = HashMap::new();
+
+        let known: HashSet<&String> = loaded
+            .iter()
+            .map(|module| &module.name)
+            .chain(modinfo.keys())
+            .collect();
+
+        for module in loaded {
+            ref_counts.insert(module.name.clone(), module.ref_count);
+            // `module.dependencies` comes from the 4th `/proc/modules`
+            // column, which is the "Used by" list -- the modules that
+            // depend on `module`, not the other way around.
+            for dep in &module.dependencies {
+                add_edge(&mut forward, &mut reverse, dep, &module.name);
+            }
+        }
+
+        for (name, info) in modinfo {
+            for dep in &info.dependencies {
+                add_edge(&mut forward, &mut reverse, name, dep);
+            }
+            for pre in &info.softdep_pre {
+                if known.contains(pre) {
+                    add_edge(&mut forward, &mut reverse, name, pre);
+                }
+            }
+            for post in &info.softdep_post {
+                if known.contains(post) {
+                    add_edge(&mut forward, &mut reverse, post, name);
+                }
+            }
+        }
+
+        DependencyGraph { forward, reverse, ref_counts }
+    }
+
+    /// Topological order to load `target`: its transitive dependencies
+    /// first, `target` last. These include the `softdep=` edges wired
+    /// in by `build`, not just hard `depends=` dependencies.
+    pub fn load_order(&self, target: &str) -> Result<Vec<String>, Error> {
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        let mut order = Vec::new();
+        self.visit(&self.forward, target, &mut visited, &mut stack, &mut order)?;
+        Ok(order)
+    }
+
+    /// Order to unload `target`: every module in the graph that
+    /// transitively depends on it first, `target` last. Dependents known
+    /// only from modinfo are included whether or not they are loaded.
+    /// Refuses with `Error::ModuleInUse(target)` when `target`'s
+    /// `/proc/modules` reference count exceeds its number of direct
+    /// dependents in the graph.
+    pub fn unload_order(&self, target: &str) -> Result<Vec<String>, Error> {
+        let mut closure = HashSet::new();
+        self.collect_dependents(target, &mut closure);
+        closure.insert(target.to_owned());
+
+        for name in &closure {
+            if let Some(dependents) = self.reverse.get(name) {
+                for dependent in dependents {
+                    if !closure.contains(dependent) {
+                        return Err(Error::ModuleInUse(name.clone()));
+                    }
+                }
+            }
+        }
+
+        let direct_dependents = self.reverse.get(target).map(|v| v.len()).unwrap_or(0);
+        if let Some(&count) = self.ref_counts.get(target) {
+            if count > direct_dependents {
+                return Err(Error::ModuleInUse(target.to_owned()));
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        let mut order = Vec::new();
+        self.visit(&self.reverse, target, &mut visited, &mut stack, &mut order)?;
+        Ok(order)
+    }
+
+    fn collect_dependents(&self, name: &str, closure: &mut HashSet<String>) {
+        if let Some(dependents) = self.reverse.get(name) {
+            for dependent in dependents {
+                if closure.insert(dependent.clone()) {
+                    self.collect_dependents(dependent, closure);
+                }
+            }
+        }
+    }
+
+    /// Depth-first post-order traversal over `edges` (either `forward`
+    /// for load order or `reverse` for unload order), reporting a
+    /// `DependencyCycle` instead of looping forever when `name` is
+    /// revisited while still on the current path.
+    fn visit(
+        &self,
+        edges: &HashMap<String, Vec<String>>,
+        name: &str,
+        visited: &mut HashSet<String>,
+        stack: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        if stack.iter().any(|on_stack| on_stack == name) {
+            let mut cycle = stack.clone();
+            cycle.push(name.to_owned());
+            return Err(Error::DependencyCycle(cycle));
+        }
+        if visited.contains(name) {
+            return Ok(());
+        }
+
+        stack.push(name.to_owned());
+        if let Some(neighbors) = edges.get(name) {
+            for neighbor in neighbors {
+                self.visit(edges, neighbor, visited, stack, order)?;
+            }
+        }
+        stack.pop();
+
+        visited.insert(name.to_owned());
+        order.push(name.to_owned());
+        Ok(())
+    }
+}
+
+fn add_edge(
+    forward: &mut HashMap<String, Vec<String>>,
+    reverse: &mut HashMap<String, Vec<String>>,
+    dependent: &str,
+    dependency: &str,
+) {
+    let deps = forward.entry(dependent.to_owned()).or_default();
+    if !deps.iter().any(|dep| dep == dependency) {
+        deps.push(dependency.to_owned());
+    }
+    let dependents = reverse.entry(dependency.to_owned()).or_default();
+    if !dependents.iter().any(|dep| dep == dependent) {
+        dependents.push(dependent.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::modinfo::ModInfo;
+    use crate::Module;
+
+    fn module(name: &str, ref_count: usize, used_by: &[&str]) -> Module {
+        Module {
+            name: name.to_owned(),
+            size: 0,
+            ref_count,
+            dependencies: used_by.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn modinfo_with_deps(deps: &[&str]) -> ModInfo {
+        ModInfo {
+            dependencies: deps.iter().map(|s| s.to_string()).collect(),
+            ..ModInfo::default()
+        }
+    }
+
+    #[test]
+    fn load_order_resolves_a_simple_chain() {
+        let mut modinfo = HashMap::new();
+        modinfo.insert("a".to_string(), modinfo_with_deps(&["b"]));
+        modinfo.insert("b".to_string(), modinfo_with_deps(&["c"]));
+        modinfo.insert("c".to_string(), modinfo_with_deps(&[]));
+
+        let graph = DependencyGraph::build(&[], &modinfo);
+        assert_eq!(graph.load_order("a").unwrap(), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn load_order_detects_cycles() {
+        let mut modinfo = HashMap::new();
+        modinfo.insert("a".to_string(), modinfo_with_deps(&["b"]));
+        modinfo.insert("b".to_string(), modinfo_with_deps(&["a"]));
+
+        let graph = DependencyGraph::build(&[], &modinfo);
+        match graph.load_order("a") {
+            Err(Error::DependencyCycle(_)) => {}
+            other => panic!("expected a dependency cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unload_order_unloads_dependents_first() {
+        let loaded = vec![module("base", 1, &["dependent"]), module("dependent", 0, &[])];
+        let graph = DependencyGraph::build(&loaded, &HashMap::new());
+        assert_eq!(graph.unload_order("base").unwrap(), vec!["dependent", "base"]);
+    }
+
+    #[test]
+    fn unload_order_refuses_when_still_in_use() {
+        let loaded = vec![module("base", 2, &["dependent"]), module("dependent", 0, &[])];
+        let graph = DependencyGraph::build(&loaded, &HashMap::new());
+        match graph.unload_order("base") {
+            Err(Error::ModuleInUse(ref name)) if name == "base" => {}
+            other => panic!("expected ModuleInUse(base), got {:?}", other),
+        }
+    }
+}
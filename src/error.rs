@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// Crate-wide error type. Every fallible operation in this crate funnels
+/// through here instead of panicking, so a single malformed module or
+/// short `/proc/modules` line can be skipped rather than aborting the
+/// whole scan.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Utf8(std::string::FromUtf8Error),
+    Elf(String),
+    MissingSection(String),
+    MalformedModinfo(String),
+    MalformedProcModules(String),
+    DependencyCycle(Vec<String>),
+    ModuleInUse(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::Utf8(ref e) => write!(f, "invalid UTF-8: {}", e),
+            Error::Elf(ref e) => write!(f, "ELF error: {}", e),
+            Error::MissingSection(ref name) => write!(f, "missing section: {}", name),
+            Error::MalformedModinfo(ref entry) => write!(f, "malformed modinfo entry: {}", entry),
+            Error::MalformedProcModules(ref line) => {
+                write!(f, "malformed /proc/modules line: {}", line)
+            }
+            Error::DependencyCycle(ref path) => {
+                write!(f, "dependency cycle detected: {}", path.join(" -> "))
+            }
+            Error::ModuleInUse(ref name) => {
+                write!(f, "module still in use: {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Error {
+        Error::Utf8(e)
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(e: walkdir::Error) -> Error {
+        Error::Io(e.into())
+    }
+}
+
+/// Turns an `Option`/`Result` into its contained value while logging a
+/// warning (with caller-supplied context) when there's nothing to
+/// unwrap, so call sites can keep going instead of unwrapping blindly.
+pub trait ResultExt<T> {
+    fn warn_err(self, context: &str) -> Option<T>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn warn_err(self, context: &str) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("warning: {}: {}", context, e.into());
+                None
+            }
+        }
+    }
+}
+
+impl<T> ResultExt<T> for Option<T> {
+    fn warn_err(self, context: &str) -> Option<T> {
+        if self.is_none() {
+            eprintln!("warning: {}: missing value", context);
+        }
+        self
+    }
+}
+
+/// Splits a whitespace-separated `/proc/modules` line into its fields,
+/// requiring at least `$n` of them (any further fields are kept), and
+/// returns a `MalformedProcModules` error instead of panicking on
+/// index-out-of-bounds when a line is short.
+#[macro_export]
+macro_rules! scan {
+    ($line:expr, $n:expr) => {{
+        let parts: Vec<&str> = $line.split_whitespace().collect();
+        if parts.len() < $n {
+            Err($crate::error::Error::MalformedProcModules($line.to_owned()))
+        } else {
+            Ok(parts)
+        }
+    }};
+}
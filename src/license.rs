@@ -0,0 +1,236 @@
+/// An SPDX-style license expression, as found (loosely) in a module's
+/// `license=` modinfo field.
+///
+/// Kernel modules don't use proper SPDX identifiers -- they use a
+/// small set of legacy tags (`GPL`, `GPL v2`, `Dual BSD/GPL`, ...) that
+/// predate the SPDX convention. `License::parse` normalizes a known
+/// legacy tag to its SPDX equivalent when the whole string is that tag;
+/// legacy tags inside a larger expression are kept as written. It falls
+/// back to `Unknown` (keeping the raw string) when the input cannot be
+/// parsed as an expression, e.g. it is empty, has unbalanced
+/// parentheses or stray tokens. Identifiers are not checked against
+/// the SPDX list, so an unrecognized single tag becomes an `Id`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum License {
+    Id(String),
+    And(Box<License>, Box<License>),
+    Or(Box<License>, Box<License>),
+    With(Box<License>, String),
+    Unknown(String),
+}
+
+/// Maps the kernel's legacy `MODULE_LICENSE()` idents to an SPDX
+/// expression string, which is then parsed the same way as any other
+/// input. Kept as a linear table rather than a `HashMap` since it's
+/// small and only ever scanned once per license string.
+const LEGACY_IDENTS: &[(&str, &str)] = &[
+    ("GPL", "GPL-2.0-only"),
+    ("GPL v2", "GPL-2.0-only"),
+    ("GPL and additional rights", "GPL-1.0-or-later"),
+    ("Dual BSD/GPL", "BSD-3-Clause OR GPL-2.0-only"),
+    ("Dual MIT/GPL", "MIT OR GPL-2.0-only"),
+    ("Dual MPL/GPL", "MPL-1.1 OR GPL-2.0-only"),
+    ("Proprietary", "LicenseRef-proprietary"),
+];
+
+/// SPDX identifiers (after normalization) that are considered
+/// compatible with the kernel's GPLv2 license for the purposes of
+/// `is_gpl_compatible`.
+#[allow(dead_code)]
+const GPL_COMPATIBLE_IDENTS: &[&str] = &[
+    "GPL-1.0-only",
+    "GPL-1.0-or-later",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "LGPL-2.0-only",
+    "LGPL-2.0-or-later",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "MIT",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "MPL-1.1",
+    "MPL-2.0",
+];
+
+impl License {
+    /// Parses a raw `license=` value into an expression tree.
+    pub fn parse(raw: &str) -> License {
+        let trimmed = raw.trim();
+        if let Some((_, replacement)) = LEGACY_IDENTS
+            .iter()
+            .find(|(ident, _)| ident.eq_ignore_ascii_case(trimmed))
+        {
+            return License::parse(replacement);
+        }
+
+        let tokens = tokenize(trimmed);
+        if tokens.is_empty() {
+            return License::Unknown(raw.to_owned());
+        }
+        let mut pos = 0;
+        match parse_or(&tokens, &mut pos) {
+            Some(license) if pos == tokens.len() => license,
+            _ => License::Unknown(raw.to_owned()),
+        }
+    }
+
+    /// Whether this license (or, for a compound expression, at least
+    /// one satisfiable branch of it) is compatible with the kernel's
+    /// GPLv2 license.
+    #[allow(dead_code)]
+    pub fn is_gpl_compatible(&self) -> bool {
+        match self {
+            License::Id(id) => GPL_COMPATIBLE_IDENTS.iter().any(|known| known.eq_ignore_ascii_case(id)),
+            License::And(lhs, rhs) => lhs.is_gpl_compatible() && rhs.is_gpl_compatible(),
+            License::Or(lhs, rhs) => lhs.is_gpl_compatible() || rhs.is_gpl_compatible(),
+            License::With(base, _) => base.is_gpl_compatible(),
+            License::Unknown(_) => false,
+        }
+    }
+
+    /// Whether this license is (or requires accepting) a proprietary
+    /// term: true for a bare `LicenseRef-proprietary` leaf, for any
+    /// branch of an `AND` expression, and for both branches of an `OR`
+    /// expression (an `OR` lets the licensee pick the free side).
+    #[allow(dead_code)]
+    pub fn is_proprietary(&self) -> bool {
+        match self {
+            License::Id(id) => id.eq_ignore_ascii_case("LicenseRef-proprietary"),
+            License::And(lhs, rhs) => lhs.is_proprietary() || rhs.is_proprietary(),
+            License::Or(lhs, rhs) => lhs.is_proprietary() && rhs.is_proprietary(),
+            License::With(base, _) => base.is_proprietary(),
+            License::Unknown(raw) => raw.to_lowercase().contains("proprietary"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    for chr in input.chars() {
+        match chr {
+            '(' | ')' => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+                tokens.push(chr.to_string());
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_or(tokens: &[String], pos: &mut usize) -> Option<License> {
+    let mut lhs = parse_and(tokens, pos)?;
+    while tokens.get(*pos).map(|t| t.eq_ignore_ascii_case("OR")) == Some(true) {
+        *pos += 1;
+        let rhs = parse_and(tokens, pos)?;
+        lhs = License::Or(Box::new(lhs), Box::new(rhs));
+    }
+    Some(lhs)
+}
+
+fn parse_and(tokens: &[String], pos: &mut usize) -> Option<License> {
+    let mut lhs = parse_with(tokens, pos)?;
+    while tokens.get(*pos).map(|t| t.eq_ignore_ascii_case("AND")) == Some(true) {
+        *pos += 1;
+        let rhs = parse_with(tokens, pos)?;
+        lhs = License::And(Box::new(lhs), Box::new(rhs));
+    }
+    Some(lhs)
+}
+
+fn parse_with(tokens: &[String], pos: &mut usize) -> Option<License> {
+    let base = parse_atom(tokens, pos)?;
+    if tokens.get(*pos).map(|t| t.eq_ignore_ascii_case("WITH")) == Some(true) {
+        *pos += 1;
+        let exception = tokens.get(*pos)?.to_owned();
+        *pos += 1;
+        return Some(License::With(Box::new(base), exception));
+    }
+    Some(base)
+}
+
+fn parse_atom(tokens: &[String], pos: &mut usize) -> Option<License> {
+    match tokens.get(*pos)?.as_str() {
+        "(" => {
+            *pos += 1;
+            let inner = parse_or(tokens, pos)?;
+            if tokens.get(*pos).map(|t| t.as_str()) != Some(")") {
+                return None;
+            }
+            *pos += 1;
+            Some(inner)
+        }
+        ")" => None,
+        ident => {
+            *pos += 1;
+            Some(License::Id(ident.to_owned()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_legacy_gpl_tag() {
+        assert_eq!(License::parse("GPL"), License::Id("GPL-2.0-only".to_owned()));
+    }
+
+    #[test]
+    fn parses_dual_bsd_gpl_into_an_or_expression() {
+        assert_eq!(
+            License::parse("Dual BSD/GPL"),
+            License::Or(
+                Box::new(License::Id("BSD-3-Clause".to_owned())),
+                Box::new(License::Id("GPL-2.0-only".to_owned())),
+            )
+        );
+    }
+
+    #[test]
+    fn parses_an_explicit_or_expression() {
+        assert_eq!(
+            License::parse("GPL-2.0-only OR MIT"),
+            License::Or(
+                Box::new(License::Id("GPL-2.0-only".to_owned())),
+                Box::new(License::Id("MIT".to_owned())),
+            )
+        );
+    }
+
+    #[test]
+    fn falls_back_to_unknown_on_unbalanced_parens() {
+        assert_eq!(
+            License::parse("(GPL-2.0-only OR MIT"),
+            License::Unknown("(GPL-2.0-only OR MIT".to_owned())
+        );
+    }
+
+    #[test]
+    fn gpl_compatible_checks_every_or_branch() {
+        assert!(License::parse("Dual BSD/GPL").is_gpl_compatible());
+        assert!(!License::parse("Proprietary").is_gpl_compatible());
+    }
+
+    #[test]
+    fn proprietary_is_detected_directly_and_through_and() {
+        assert!(License::parse("Proprietary").is_proprietary());
+        assert!(License::parse("LicenseRef-proprietary AND GPL-2.0-only").is_proprietary());
+        assert!(!License::parse("Dual BSD/GPL").is_proprietary());
+    }
+}
@@ -2,183 +2,200 @@ extern crate libc;
 extern crate elf;
 extern crate flate2;
 extern crate walkdir;
+extern crate xz2;
+extern crate zstd;
 
-use std::io::{BufReader,BufRead};
+#[macro_use]
+mod error;
+mod license;
+mod modinfo;
+mod builtin;
+mod compress;
+mod depgraph;
+mod resolve;
+
+use std::io::{BufReader, BufRead};
 use std::fs::File;
 use std::io::Read;
-use std::io::Cursor;
+use std::mem::MaybeUninit;
 
-const F_NAME : usize = 0;
-const F_SIZE : usize = 1;
-const F_USECOUNT : usize = 2;
-const F_DEPENDENCIES : usize = 3;
+use builtin::get_builtin_modinfo;
+use compress::{Compression, MODULE_SUFFIXES};
+use depgraph::DependencyGraph;
+use error::{Error, ResultExt};
+use modinfo::ModInfo;
+use resolve::Resolver;
 
-#[allow(dead_code)]
-struct Module {
-    name: String,
-    size: usize,
-    ref_count: usize,
-    dependencies: Vec<String>,
-}
-
-#[derive(Debug, Default)]
-struct ModuleParameter {
-    name: String,
-    description: String,
-    kind: String,
-}
+const F_NAME: usize = 0;
+const F_SIZE: usize = 1;
+const F_USECOUNT: usize = 2;
+const F_DEPENDENCIES: usize = 3;
+const F_COUNT: usize = 4;
 
-#[derive(Debug, Default)]
-struct ModInfo {
-    license: String,
-    parameters: std::collections::HashMap<String, ModuleParameter>,
-    alias: Vec<String>,
-    dependencies: Vec<String>,
-    description: String,
-    authors: Vec<String>,
-    vermagic: String,
-    intree: bool,
-    firewares: Vec<String>,
+#[allow(dead_code)]
+pub(crate) struct Module {
+    pub(crate) name: String,
+    pub(crate) size: usize,
+    pub(crate) ref_count: usize,
+    pub(crate) dependencies: Vec<String>,
 }
 
-fn get_kernel_release() -> String {
+fn get_kernel_release() -> Result<String, Error> {
     unsafe {
-        let mut result : libc::utsname = std::mem::uninitialized();
-        libc::uname(&mut result);
-        let release : Vec<u8> = std::mem::transmute::<[i8; 65], [u8; 65]>(result.release)
+        let mut uts = MaybeUninit::<libc::utsname>::uninit();
+        if libc::uname(uts.as_mut_ptr()) != 0 {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        let result = uts.assume_init();
+        let release: Vec<u8> = std::mem::transmute::<[i8; 65], [u8; 65]>(result.release)
             .iter()
-            .filter(|&&chr| { chr != 0u8 })
-            .map(|&refbox| { refbox.to_owned() })
+            .filter(|&&chr| chr != 0u8)
+            .map(|&refbox| refbox.to_owned())
             .collect();
 
-        String::from_utf8(release).unwrap()
+        Ok(String::from_utf8(release)?)
     }
 }
 
-fn get_module_paths() -> Vec<String> {
-    let release = get_kernel_release();
+fn get_module_paths() -> Result<Vec<String>, Error> {
+    let release = get_kernel_release()?;
     let rootdir = format!("/lib/modules/{}", release);
 
     let mut result = vec![];
     for entry in walkdir::WalkDir::new(rootdir) {
-        let entry = entry.unwrap();
-        let filename = entry.path().to_str().unwrap();
-        if filename.ends_with(".ko.gz") {
+        let entry = match entry.warn_err("walking module tree") {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let filename = match entry.path().to_str() {
+            Some(filename) => filename,
+            None => continue,
+        };
+        if MODULE_SUFFIXES.iter().any(|suffix| filename.ends_with(suffix)) {
             result.push(filename.to_owned());
         }
     }
-    result
+    Ok(result)
 }
 
-fn get_modinfo_from_file(path: String) -> Result<ModInfo, &'static str> {
-    let mut gzipped = File::open(&path).unwrap();
+fn get_modinfo_from_file(path: String) -> Result<ModInfo, Error> {
+    let compression = Compression::from_filename(&path)
+        .ok_or_else(|| Error::MalformedModinfo(format!("unrecognized module suffix: {}", path)))?;
+
+    let mut raw = File::open(&path)?;
     let mut compressed = Vec::new();
-    gzipped.read_to_end(&mut compressed).unwrap();
-    let mut buffer = Cursor::new(&compressed);
-
-    let mut decoder = flate2::read::GzDecoder::new(&mut buffer).unwrap();
-    let mut buf = Cursor::new(Vec::new());
-    decoder.read_to_end(buf.get_mut()).unwrap();
-
-    let file = match elf::File::open_stream(&mut buf) {
-        Ok(f) => f,
-        Err(e) => panic!("Error: {:?}", e),
-    };
-
-    match file.get_section(".modinfo") {
-        Some(s) => {
-            let mut result : ModInfo = ModInfo::default();
-            for one in s.data.split(|chr| *chr == 0) {
-                let entry = String::from_utf8(one.to_vec()).unwrap();
-                if entry.starts_with("parm=") {
-                    let tmp = &entry["parm=".len()..];
-                    let (name, description) = tmp.split_at(tmp.find(":").unwrap());
-                    result.parameters.insert(name.to_owned(), ModuleParameter {
-                        name: name.to_owned(),
-                        description: description[1..].to_owned(),
-                        kind: "".to_owned(),
-                    });
-                } else if entry.starts_with("parmtype=") {
-                    let tmp = &entry["parmtype=".len()..];
-                    let mut split = tmp.split(":");
-                    if let Some(x) = result.parameters.get_mut(&split.next().unwrap().to_owned()) {
-                        (*x).kind = split.next().unwrap().to_owned();
-                    }
-                } else if entry.starts_with("license=") {
-                    result.license = (&entry["license=".len()..]).to_owned();
-                } else if entry.starts_with("alias=") {
-                    result.alias.push((&entry["alias=".len()..]).to_owned());
-                } else if entry.starts_with("depends=") {
-                    for dependency in (&entry["depends=".len()..]).split(",") {
-                        result.dependencies.push(dependency.to_owned());
-                    }
-                } else if entry.starts_with("description=") {
-                    result.description = (&entry["description=".len()..]).to_owned();
-                } else if entry.starts_with("author=") {
-                    result.authors.push((&entry["author=".len()..]).to_owned());
-                } else if entry.starts_with("vermagic=") {
-                    result.vermagic = (&entry["vermagic=".len()..]).to_owned();
-                } else if entry.starts_with("intree=") {
-                    result.intree = (&entry["intree=".len()..]).to_owned() == "Y";
-                } else if entry.starts_with("firmware=") {
-                    result.firewares.push((&entry["fireware=".len()..]).to_owned());
-                } else if entry.starts_with("version=") {
-                    // TODO
-                } else if entry.starts_with("srcversion=") {
-                    // TODO
-                } else if entry.starts_with("staging=") {
-                    // TODO
-                } else if entry.starts_with("release_date=") {
-                    // TODO
-                } else if entry.starts_with("softdep=") {
-                    // TODO
-                } else if entry == "" {
-                } else {
-                    println!("Unmatched {}, {}", &path, &entry);
-                }
-            };
-            Ok(result)
+    raw.read_to_end(&mut compressed)?;
+
+    let mut buf = compress::decompress(compression, &compressed)?;
+
+    let file = elf::File::open_stream(&mut buf)
+        .map_err(|e| Error::Elf(format!("{:?}", e)))?;
+
+    let section = file
+        .get_section(".modinfo")
+        .ok_or_else(|| Error::MissingSection(".modinfo".to_owned()))?;
+
+    let mut result: ModInfo = ModInfo::default();
+    for one in section.data.split(|chr| *chr == 0) {
+        let entry = String::from_utf8(one.to_vec())?;
+        if !entry.is_empty() {
+            result.apply_field(&entry)?;
+        }
+    }
+    Ok(result)
+}
+
+fn parse_proc_modules_line(text: &str) -> Result<Module, Error> {
+    let parts = scan!(text, F_COUNT)?;
+    Ok(Module {
+        name: parts[F_NAME].to_string(),
+        size: parts[F_SIZE]
+            .parse::<usize>()
+            .map_err(|_| Error::MalformedProcModules(text.to_owned()))?,
+        ref_count: parts[F_USECOUNT]
+            .parse::<usize>()
+            .map_err(|_| Error::MalformedProcModules(text.to_owned()))?,
+        dependencies: match parts[F_DEPENDENCIES] {
+            "-" => vec![],
+            deps => deps
+                .split(",")
+                .filter(|&one| !one.is_empty())
+                .map(|one| one.to_string())
+                .collect(),
         },
-        None => Err("Cannot find .modinfo section"),
+    })
+}
+
+fn get_loaded_modules() -> Result<Vec<Module>, Error> {
+    let file = File::open("/proc/modules")?;
+    let mut result = vec![];
+    for line in BufReader::new(file).lines() {
+        let text = line?;
+        if let Some(module) = parse_proc_modules_line(&text).warn_err("parsing /proc/modules line") {
+            result.push(module);
+        }
     }
+    Ok(result)
+}
+
+/// Derives a module's canonical name from its file path, the same way
+/// `modprobe` does: strip the directory and compression/`.ko` suffix,
+/// then fold dashes to underscores so `/lib/.../usb-storage.ko.xz`
+/// resolves to `usb_storage`, matching how it shows up in
+/// `/proc/modules` and `depends=` fields.
+pub(crate) fn module_name_from_path(path: &str) -> Option<String> {
+    let filename = path.rsplit('/').next()?;
+    let suffix = MODULE_SUFFIXES.iter().find(|suffix| filename.ends_with(*suffix))?;
+    let stem = &filename[..filename.len() - suffix.len()];
+    Some(stem.replace('-', "_"))
 }
 
-fn get_loaded_modules() -> Vec<Module> {
-    match File::open("/proc/modules") {
-        Ok(file) => {
-            let mut result = vec![];
-            for line in BufReader::new(file).lines() {
-                let text = line.unwrap();
-                let parts : Vec<&str> = text.split(" ").collect();
-                let module = Module {
-                    name: parts[F_NAME].to_string(),
-                    size: parts[F_SIZE].parse::<usize>().unwrap(),
-                    ref_count: parts[F_USECOUNT].parse::<usize>().unwrap(),
-                    dependencies: {
-                        match parts[F_DEPENDENCIES] {
-                            "-" => vec![],
-                            _ => parts[F_DEPENDENCIES].split(",")
-                            .filter(|&one| { one != "" })
-                            .map(|one| { one.to_string() })
-                            .collect(),
-                        }
-                    },
-                };
-                result.push(module);
+fn main() {
+    let loaded = get_loaded_modules().warn_err("reading /proc/modules").unwrap_or_default();
+    println!(
+        "Module {:?} are loaded",
+        loaded.iter().map(|module| module.name.clone()).collect::<Vec<String>>()
+    );
+
+    let mut modinfo_by_name: std::collections::HashMap<String, ModInfo> = std::collections::HashMap::new();
+
+    let paths = get_module_paths().warn_err("enumerating module paths").unwrap_or_default();
+    for module_path in paths {
+        let path_for_warning = module_path.clone();
+        if let Some(info) = get_modinfo_from_file(module_path.clone())
+            .warn_err(&format!("reading modinfo from {}", path_for_warning))
+        {
+            if let Some(name) = module_name_from_path(&module_path) {
+                modinfo_by_name.insert(name, info);
             }
-            result
         }
-        Err(e) => {
-            // fallback in case of failure.
-            // you could log the error, panic, or do anything else.
-            panic!("{}", e);
+    }
+
+    let release = get_kernel_release().warn_err("reading kernel release");
+
+    if let Some(release) = release.as_ref() {
+        if let Some(builtin) = get_builtin_modinfo(release).warn_err("reading modules.builtin.modinfo") {
+            println!("Built-in modules: {:?}", builtin.keys().collect::<Vec<&String>>());
+            modinfo_by_name.extend(builtin);
         }
     }
-}
 
-fn main() {
-    println!("Module {:?} are loaded", get_loaded_modules().into_iter().map(|module| module.name).collect::<Vec<String>>());
-    for module_path in get_module_paths() {
-        get_modinfo_from_file(module_path).unwrap();
-    };
+    let graph = DependencyGraph::build(&loaded, &modinfo_by_name);
+    if let Some(requested) = std::env::args().nth(1) {
+        let target = release
+            .as_ref()
+            .and_then(|release| Resolver::build(release, &modinfo_by_name).warn_err("building module resolver"))
+            .and_then(|resolver| resolver.resolve(&requested))
+            .and_then(|path| module_name_from_path(path.to_str()?))
+            .unwrap_or(requested);
+
+        match graph.load_order(&target) {
+            Ok(order) => println!("Load order for {}: {:?}", target, order),
+            Err(e) => eprintln!("warning: computing load order for {}: {}", target, e),
+        }
+        match graph.unload_order(&target) {
+            Ok(order) => println!("Unload order for {}: {:?}", target, order),
+            Err(e) => eprintln!("warning: computing unload order for {}: {}", target, e),
+        }
+    }
 }
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use super::error::Error;
+use super::license::License;
+
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct ModuleParameter {
+    pub name: String,
+    pub description: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ModInfo {
+    pub license: Option<License>,
+    pub parameters: HashMap<String, ModuleParameter>,
+    pub alias: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub description: String,
+    pub authors: Vec<String>,
+    pub vermagic: String,
+    pub intree: bool,
+    pub firewares: Vec<String>,
+    pub softdep_pre: Vec<String>,
+    pub softdep_post: Vec<String>,
+}
+
+impl ModInfo {
+    /// Applies a single `key=value` modinfo entry to this struct. Used
+    /// both for entries pulled out of the ELF `.modinfo` section of a
+    /// loadable module and for the module-prefixed entries found in
+    /// `modules.builtin.modinfo`, so the two sources stay in sync.
+    pub fn apply_field(&mut self, entry: &str) -> Result<(), Error> {
+        if let Some(tmp) = entry.strip_prefix("parm=") {
+            let colon = tmp
+                .find(':')
+                .ok_or_else(|| Error::MalformedModinfo(entry.to_owned()))?;
+            let (name, description) = tmp.split_at(colon);
+            self.parameters.insert(name.to_owned(), ModuleParameter {
+                name: name.to_owned(),
+                description: description[1..].to_owned(),
+                kind: "".to_owned(),
+            });
+        } else if let Some(tmp) = entry.strip_prefix("parmtype=") {
+            let mut split = tmp.split(':');
+            let name = split
+                .next()
+                .ok_or_else(|| Error::MalformedModinfo(entry.to_owned()))?;
+            if let Some(x) = self.parameters.get_mut(name) {
+                x.kind = split
+                    .next()
+                    .ok_or_else(|| Error::MalformedModinfo(entry.to_owned()))?
+                    .to_owned();
+            }
+        } else if let Some(tmp) = entry.strip_prefix("license=") {
+            self.license = Some(License::parse(tmp));
+        } else if let Some(tmp) = entry.strip_prefix("alias=") {
+            self.alias.push(tmp.to_owned());
+        } else if let Some(tmp) = entry.strip_prefix("depends=") {
+            for dependency in tmp.split(',') {
+                if !dependency.is_empty() {
+                    self.dependencies.push(dependency.to_owned());
+                }
+            }
+        } else if let Some(tmp) = entry.strip_prefix("description=") {
+            self.description = tmp.to_owned();
+        } else if let Some(tmp) = entry.strip_prefix("author=") {
+            self.authors.push(tmp.to_owned());
+        } else if let Some(tmp) = entry.strip_prefix("vermagic=") {
+            self.vermagic = tmp.to_owned();
+        } else if let Some(tmp) = entry.strip_prefix("intree=") {
+            self.intree = tmp == "Y";
+        } else if let Some(tmp) = entry.strip_prefix("firmware=") {
+            self.firewares.push(tmp.to_owned());
+        } else if entry.starts_with("version=")
+            || entry.starts_with("srcversion=")
+            || entry.starts_with("staging=")
+            || entry.starts_with("release_date=")
+        {
+            // Not currently surfaced on `ModInfo`.
+        } else if let Some(tmp) = entry.strip_prefix("softdep=") {
+            // e.g. "softdep=pre: dep1 dep2 post: dep3" -- a sequence of
+            // module names, grouped by the most recently seen "pre:"
+            // or "post:" marker.
+            let mut section: Option<bool> = None; // Some(true) = pre, Some(false) = post
+            for token in tmp.split_whitespace() {
+                match token {
+                    "pre:" => section = Some(true),
+                    "post:" => section = Some(false),
+                    _ => match section {
+                        Some(true) => self.softdep_pre.push(token.to_owned()),
+                        Some(false) => self.softdep_post.push(token.to_owned()),
+                        None => {}
+                    },
+                }
+            }
+        } else if entry.is_empty() {
+        } else {
+            eprintln!("warning: unmatched modinfo entry {}", entry);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use super::error::Error;
+use super::modinfo::ModInfo;
+use super::module_name_from_path;
+
+/// Parses `/lib/modules/<release>/modules.dep`: each line is
+/// `path: dep-path dep-path ...`, mapping a module's on-disk path to
+/// the paths of the modules it depends on.
+pub fn parse_modules_dep(release: &str) -> Result<HashMap<String, Vec<String>>, Error> {
+    let path = format!("/lib/modules/{}/modules.dep", release);
+    let file = File::open(&path)?;
+    let mut result = HashMap::new();
+    for line in BufReader::new(file).lines() {
+        let text = line?;
+        if text.trim().is_empty() {
+            continue;
+        }
+        let colon = text
+            .find(':')
+            .ok_or_else(|| Error::MalformedModinfo(text.clone()))?;
+        let (module_path, deps) = text.split_at(colon);
+        let deps = deps[1..]
+            .split_whitespace()
+            .map(|dep| dep.to_owned())
+            .collect();
+        result.insert(module_path.to_owned(), deps);
+    }
+    Ok(result)
+}
+
+/// Parses `/lib/modules/<release>/modules.alias`: lines of the form
+/// `alias <pattern> <module>`, returning the `(pattern, module)` pairs
+/// in file order.
+pub fn parse_modules_alias(release: &str) -> Result<Vec<(String, String)>, Error> {
+    let path = format!("/lib/modules/{}/modules.alias", release);
+    let file = File::open(&path)?;
+    let mut result = vec![];
+    for line in BufReader::new(file).lines() {
+        let text = line?;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let parts = scan!(text, 3)?;
+        if parts[0] != "alias" {
+            continue;
+        }
+        result.push((parts[1].to_owned(), parts[2].to_owned()));
+    }
+    Ok(result)
+}
+
+/// Resolves a module name or alias (PCI/USB modalias string, or a
+/// friendly alias like `fuse`) to its on-disk path, the way `modprobe`
+/// does: an exact module-name match first, then a glob match against
+/// every known alias pattern.
+///
+/// Aliases come from two sources: the depmod-generated
+/// `modules.alias` index and each module's own `alias=` modinfo
+/// entries, so both participate in lookup.
+pub struct Resolver {
+    name_to_path: HashMap<String, PathBuf>,
+    aliases: Vec<(String, String)>,
+}
+
+impl Resolver {
+    pub fn build(release: &str, modinfo: &HashMap<String, ModInfo>) -> Result<Resolver, Error> {
+        let deps = parse_modules_dep(release)?;
+        let mut name_to_path = HashMap::new();
+        for module_path in deps.keys() {
+            if let Some(name) = module_name_from_path(module_path) {
+                name_to_path.insert(name, PathBuf::from(module_path));
+            }
+        }
+
+        let mut aliases = parse_modules_alias(release)?;
+        for (name, info) in modinfo {
+            for alias in &info.alias {
+                aliases.push((alias.clone(), name.clone()));
+            }
+        }
+
+        Ok(Resolver { name_to_path, aliases })
+    }
+
+    pub fn resolve(&self, name_or_alias: &str) -> Option<PathBuf> {
+        if let Some(path) = self.name_to_path.get(name_or_alias) {
+            return Some(path.clone());
+        }
+        self.aliases
+            .iter()
+            .find(|(pattern, module)| {
+                glob_match(pattern, name_or_alias) && self.name_to_path.contains_key(module)
+            })
+            .and_then(|(_, module)| self.name_to_path.get(module).cloned())
+    }
+}
+
+/// Matches `text` against a shell-style glob `pattern`, supporting the
+/// `*`, `?`, and `[...]` wildcards used in modalias strings (including
+/// `[!...]`/`[^...]` negation and `a-z` ranges inside a class).
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_rec(&pattern, &text)
+}
+
+fn glob_match_rec(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => {
+            glob_match_rec(&pattern[1..], text)
+                || (!text.is_empty() && glob_match_rec(pattern, &text[1..]))
+        }
+        Some('?') => !text.is_empty() && glob_match_rec(&pattern[1..], &text[1..]),
+        Some('[') => match pattern[1..].iter().position(|&chr| chr == ']') {
+            Some(offset) => {
+                let close = offset + 1;
+                if text.is_empty() {
+                    return false;
+                }
+                let (negate, class_start) = match pattern.get(1) {
+                    Some('!') | Some('^') => (true, 2),
+                    _ => (false, 1),
+                };
+                let class = &pattern[class_start..close];
+                let matched = char_in_class(class, text[0]) != negate;
+                matched && glob_match_rec(&pattern[close + 1..], &text[1..])
+            }
+            None => !text.is_empty() && text[0] == '[' && glob_match_rec(&pattern[1..], &text[1..]),
+        },
+        Some(&chr) => !text.is_empty() && text[0] == chr && glob_match_rec(&pattern[1..], &text[1..]),
+    }
+}
+
+fn char_in_class(class: &[char], chr: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if chr >= class[i] && chr <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == chr {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_matches_any_run_including_empty() {
+        assert!(glob_match("pci:v*d*", "pci:v00008086d00001234"));
+        assert!(glob_match("usb*", "usb"));
+        assert!(!glob_match("pci:v*", "usb:v00008086"));
+    }
+
+    #[test]
+    fn question_mark_matches_exactly_one_char() {
+        assert!(glob_match("ab?", "abc"));
+        assert!(!glob_match("ab?", "ab"));
+        assert!(!glob_match("ab?", "abcd"));
+    }
+
+    #[test]
+    fn char_class_matches_any_listed_char() {
+        assert!(glob_match("usb:v[0123]*", "usb:v1234"));
+        assert!(!glob_match("usb:v[0123]*", "usb:v9234"));
+    }
+
+    #[test]
+    fn negated_char_class_excludes_listed_chars() {
+        assert!(glob_match("usb:v[!0123]*", "usb:v9234"));
+        assert!(!glob_match("usb:v[!0123]*", "usb:v1234"));
+        assert!(glob_match("usb:v[^0123]*", "usb:v9234"));
+    }
+
+    #[test]
+    fn char_class_range_matches_inclusive_bounds() {
+        assert!(glob_match("v[a-f]", "va"));
+        assert!(glob_match("v[a-f]", "vf"));
+        assert!(!glob_match("v[a-f]", "vg"));
+    }
+}